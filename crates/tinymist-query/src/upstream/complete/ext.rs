@@ -1,12 +1,15 @@
 use super::{Completion, CompletionContext, CompletionKind};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
 
 use ecow::{eco_format, EcoString};
 use typst::foundations::Value;
 use typst::syntax::ast::AstNode;
 use typst::syntax::{ast, SyntaxKind};
 
-use crate::analysis::{analyze_dyn_signature, analyze_import, resolve_callee};
+use crate::analysis::{
+    analyze_dyn_signature, analyze_expr, analyze_import, analyze_import_path, resolve_callee,
+};
 use crate::upstream::plain_docs_sentence;
 
 impl<'a, 'w> CompletionContext<'a, 'w> {
@@ -129,6 +132,9 @@ impl<'a, 'w> CompletionContext<'a, 'w> {
             }
         }
 
+        self.flyimport_completions(&defined, &scope);
+        self.keyword_snippet_completions(in_math);
+
         for (name, kind) in defined {
             if !name.is_empty() {
                 if kind == CompletionKind::Func {
@@ -141,6 +147,7 @@ impl<'a, 'w> CompletionContext<'a, 'w> {
                         detail: None,
                         // todo: only vscode and neovim (0.9.1) support this
                         command: Some("editor.action.triggerSuggest"),
+                        additional_text_edit: None,
                     });
                 } else {
                     self.completions.push(Completion {
@@ -149,13 +156,289 @@ impl<'a, 'w> CompletionContext<'a, 'w> {
                         apply: None,
                         detail: None,
                         command: None,
+                        additional_text_edit: None,
                     });
                 }
             }
         }
+
+        if let Some(param_list) = self.param_list_at_cursor() {
+            fn_param_completions(self, param_list);
+        }
+
+        if let Some(field_access) = self
+            .leaf
+            .parent()
+            .cloned()
+            .and_then(|parent| parent.cast::<ast::FieldAccess>())
+        {
+            postfix_completions(self, field_access);
+        }
+    }
+
+    /// The `Params` list the cursor is directly inside of, if any, e.g.
+    /// while writing a new closure or `let`-bound function's parameter list.
+    ///
+    /// Mirrors `in_invalid_keyword_position`'s boundary-aware walk: a
+    /// `Params` found only after crossing an `Args`/block boundary (e.g. a
+    /// nested call inside a parameter's default value) doesn't count, since
+    /// the cursor isn't writing a parameter there.
+    fn param_list_at_cursor(&self) -> Option<ast::Params<'a>> {
+        param_list_ancestor(&self.leaf)
+    }
+
+    /// Offer snippet completions for keywords that open a block, e.g. `for`
+    /// completing to `for ${1:x} in ${2:collection} { $0 }`.
+    ///
+    /// Like rust-analyzer's "magic completions", these come with the
+    /// scaffolding already in place instead of just the bare keyword. Math
+    /// mode only offers the constructs that are valid there.
+    fn keyword_snippet_completions(&mut self, in_math: bool) {
+        if self.in_invalid_keyword_position() {
+            return;
+        }
+
+        let snippets: &[(&str, &str, Option<&str>)] = if in_math {
+            &[
+                ("if", "if ${1:cond} {\n\t$0\n}", None),
+                ("for", "for ${1:x} in ${2:collection} {\n\t$0\n}", None),
+                ("let", "let ${1:name} = $0", None),
+            ]
+        } else {
+            &[
+                ("if", "if ${1:cond} {\n\t$0\n}", None),
+                ("else", "else {\n\t$0\n}", None),
+                ("for", "for ${1:x} in ${2:collection} {\n\t$0\n}", None),
+                ("while", "while ${1:cond} {\n\t$0\n}", None),
+                ("let", "let ${1:name} = $0", None),
+                ("show", "show ${1}: $0", None),
+                ("set", "set ${1:elem}($0)", None),
+                ("context", "context $0", None),
+                (
+                    "import",
+                    "import \"$1\"",
+                    Some("editor.action.triggerSuggest"),
+                ),
+            ]
+        };
+
+        for (name, apply, command) in snippets {
+            self.completions.push(Completion {
+                kind: CompletionKind::Syntax,
+                label: (*name).into(),
+                apply: Some((*apply).into()),
+                detail: None,
+                command: *command,
+                additional_text_edit: None,
+            });
+        }
+    }
+
+    /// Whether the cursor sits somewhere a statement-only keyword (`let`,
+    /// `show`, `set`, ...) would be syntactically invalid, e.g. inside an
+    /// argument list, parameter list, dictionary, or array.
+    ///
+    /// Walks up through non-statement-boundary ancestors rather than only
+    /// checking the direct parent, so a nested sub-expression like the `x +
+    /// <cursor>` in `f(x + <cursor>)` is still recognized as being inside the
+    /// call's `Args`.
+    fn in_invalid_keyword_position(&self) -> bool {
+        is_invalid_keyword_position(&self.leaf)
+    }
+
+    /// Offer completions for symbols that aren't imported yet, inserting the
+    /// matching `#import` statement when the completion is accepted.
+    ///
+    /// Like rust-analyzer's flyimport, this looks beyond what's visible at
+    /// the cursor and searches modules known to the analysis context
+    /// (installed/preview packages and sibling files reachable from the
+    /// current world) for names that fuzzy-match the prefix being typed.
+    ///
+    /// `scope` is the `global`/`math` library scope already computed by the
+    /// caller: a name resolvable from there needs no import, so it's
+    /// excluded just like a name already in `defined`.
+    //
+    // todo: cache `analyze_import_path` results across completion requests,
+    // same as the pre-existing import-scope loop above.
+    fn flyimport_completions(
+        &mut self,
+        defined: &BTreeMap<EcoString, CompletionKind>,
+        scope: &typst::foundations::Scope,
+    ) {
+        let Some(ident) = self.leaf.cast::<ast::Ident>() else {
+            return;
+        };
+        let prefix = ident.get();
+        // Don't do the candidate-module walk for a prefix too short to
+        // meaningfully narrow anything down; this is the hottest path in
+        // `scope_completions_` and runs on every relevant keystroke.
+        if prefix.len() < 2 {
+            return;
+        }
+
+        let already_imported = self.imported_module_paths();
+
+        let mut seen = BTreeSet::new();
+        for path in self.ctx.import_candidates() {
+            if already_imported.contains(&path) || !seen.insert(path.clone()) {
+                continue;
+            }
+
+            let Some(value) = analyze_import_path(self.world(), &path) else {
+                continue;
+            };
+            let Some(module_scope) = value.scope() else {
+                continue;
+            };
+
+            for (name, v) in module_scope.iter() {
+                if defined.contains_key(name)
+                    || scope.get(name).is_some()
+                    || !fuzzy_match(prefix, name)
+                {
+                    continue;
+                }
+
+                let kind = match v {
+                    Value::Func(..) => CompletionKind::Func,
+                    Value::Module(..) => CompletionKind::Module,
+                    Value::Type(..) => CompletionKind::Type,
+                    _ => CompletionKind::Constant,
+                };
+
+                self.completions.push(Completion {
+                    kind,
+                    label: name.clone(),
+                    apply: None,
+                    detail: Some(eco_format!("from \"{path}\"")),
+                    command: None,
+                    additional_text_edit: Some(self.import_insertion_edit(&path)),
+                });
+            }
+        }
+    }
+
+    /// Paths already reachable via a `#import` in an ancestor of the cursor,
+    /// or one of that ancestor's preceding siblings.
+    ///
+    /// Uses the same ancestor/prev-sibling traversal as `scope_completions_`
+    /// above, since a `#import` is almost always a preceding sibling of an
+    /// ancestor block rather than a strict ancestor itself.
+    fn imported_module_paths(&self) -> BTreeSet<EcoString> {
+        let mut paths = BTreeSet::new();
+
+        let mut ancestor = Some(self.leaf.clone());
+        while let Some(node) = &ancestor {
+            let mut sibling = Some(node.clone());
+            while let Some(node) = &sibling {
+                if let Some(import) = node.cast::<ast::ModuleImport>() {
+                    if let ast::Expr::Str(path) = import.source() {
+                        paths.insert(path.get());
+                    }
+                }
+                sibling = node.prev_sibling();
+            }
+
+            ancestor = node.parent().cloned();
+        }
+
+        paths
+    }
+
+    /// Compute the text edit that inserts `#import "path": *` right after the
+    /// last top-level module import, or at the start of the file if there is
+    /// none yet.
+    fn import_insertion_edit(&self, path: &str) -> (Range<usize>, EcoString) {
+        let insert_at = import_insertion_point(&self.root);
+        (insert_at..insert_at, import_insertion_text(path, insert_at))
+    }
+}
+
+/// The byte offset right after the last top-level module import, or `0` if
+/// there is none yet.
+fn import_insertion_point(root: &typst::syntax::LinkedNode) -> usize {
+    root.children()
+        .filter(|child| child.is::<ast::ModuleImport>())
+        .last()
+        .map(|last| last.range().end)
+        .unwrap_or(0)
+}
+
+/// The text to insert at `insert_at` (as computed by `import_insertion_point`)
+/// to add a new `#import`. Leads with a newline when appending after an
+/// existing import so the two don't end up glued onto the same line.
+fn import_insertion_text(path: &str, insert_at: usize) -> EcoString {
+    if insert_at == 0 {
+        eco_format!("#import \"{path}\": *\n")
+    } else {
+        eco_format!("\n#import \"{path}\": *\n")
     }
 }
 
+/// The nearest `Params` ancestor of `leaf`, stopping at an `Args`/block
+/// boundary first so a nested sub-expression inside a parameter's default
+/// value (e.g. the call args in `(x: foo(1, <cursor>))`) doesn't resolve to
+/// the outer `Params`. See `CompletionContext::param_list_at_cursor`.
+fn param_list_ancestor<'a>(leaf: &typst::syntax::LinkedNode<'a>) -> Option<ast::Params<'a>> {
+    let mut ancestor = Some(leaf.clone());
+    while let Some(node) = ancestor {
+        if let Some(params) = node.cast::<ast::Params>() {
+            return Some(params);
+        }
+        match node.kind() {
+            SyntaxKind::Args
+            | SyntaxKind::Named
+            | SyntaxKind::Dict
+            | SyntaxKind::Array
+            | SyntaxKind::CodeBlock
+            | SyntaxKind::ContentBlock
+            | SyntaxKind::Code
+            | SyntaxKind::Markup
+            | SyntaxKind::Closure => return None,
+            _ => {}
+        }
+        ancestor = node.parent().cloned();
+    }
+    None
+}
+
+/// Whether `leaf` sits somewhere a statement-only keyword (`let`, `show`,
+/// `set`, ...) would be syntactically invalid, e.g. inside an argument list,
+/// parameter list, dictionary, or array. See
+/// `CompletionContext::in_invalid_keyword_position`.
+fn is_invalid_keyword_position(leaf: &typst::syntax::LinkedNode) -> bool {
+    let mut ancestor = leaf.parent().cloned();
+    while let Some(node) = ancestor {
+        match node.kind() {
+            SyntaxKind::Args
+            | SyntaxKind::Named
+            | SyntaxKind::Dict
+            | SyntaxKind::Array
+            | SyntaxKind::Params => {
+                return true;
+            }
+            SyntaxKind::CodeBlock
+            | SyntaxKind::ContentBlock
+            | SyntaxKind::Code
+            | SyntaxKind::Markup
+            | SyntaxKind::Closure => return false,
+            _ => {}
+        }
+        ancestor = node.parent().cloned();
+    }
+    false
+}
+
+/// Whether `name` fuzzy-matches `prefix`, i.e. `prefix`'s characters appear in
+/// `name` in order, ignoring case.
+fn fuzzy_match(prefix: &str, name: &str) -> bool {
+    let mut name_chars = name.chars().flat_map(char::to_lowercase);
+    prefix
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|c| name_chars.any(|n| n == c))
+}
+
 /// Add completions for the parameters of a function.
 pub fn param_completions<'a>(
     ctx: &mut CompletionContext<'a, '_>,
@@ -214,6 +497,7 @@ pub fn param_completions<'a>(
                 // editor.action.triggerSuggest as command on a suggestion to
                 // "manually" retrigger suggest after inserting one
                 command: Some("editor.action.triggerSuggest"),
+                additional_text_edit: None,
             });
         }
 
@@ -227,6 +511,100 @@ pub fn param_completions<'a>(
     }
 }
 
+/// Add completions for a new closure/function parameter based on how
+/// parameters of that kind recur elsewhere in the file.
+///
+/// Mirrors rust-analyzer's `complete_fn_param`: `name`/`name: default` pairs
+/// that are reused across the document (`body`, `loc`, `it`, ...) are ranked
+/// by frequency and offered, so defining a new function with a conventional
+/// parameter is one keystroke.
+pub fn fn_param_completions<'a>(ctx: &mut CompletionContext<'a, '_>, param_list: ast::Params<'a>) {
+    let current: BTreeSet<EcoString> = param_list
+        .children()
+        .flat_map(|param| match param {
+            ast::Param::Pos(pattern) => pattern.bindings(),
+            ast::Param::Named(named) => vec![named.name()],
+            ast::Param::Spread(spread) => spread.sink_ident().into_iter().collect(),
+        })
+        .map(|ident| ident.get().clone())
+        .collect();
+
+    let mut freq: BTreeMap<EcoString, usize> = BTreeMap::new();
+    collect_fn_params(&ctx.root.clone(), param_list.to_untyped().range(), &mut freq);
+
+    for rendered in rank_params(freq, &current) {
+        ctx.completions.push(Completion {
+            kind: CompletionKind::Param,
+            label: rendered.clone(),
+            apply: Some(rendered),
+            detail: None,
+            command: None,
+            additional_text_edit: None,
+        });
+    }
+
+    if ctx.before.ends_with(',') {
+        ctx.enrich(" ", "");
+    }
+}
+
+/// Rank rendered parameters by how often they recur, excluding any whose
+/// name already appears in `current` (the parameter list being written),
+/// most frequent first and ties broken alphabetically.
+fn rank_params(freq: BTreeMap<EcoString, usize>, current: &BTreeSet<EcoString>) -> Vec<EcoString> {
+    let mut ranked: Vec<_> = freq
+        .into_iter()
+        .filter(|(rendered, _)| {
+            let name = rendered.split(':').next().unwrap_or(rendered);
+            !current.contains(name)
+        })
+        .collect();
+    ranked.sort_by(|(a_name, a_freq), (b_name, b_freq)| {
+        b_freq.cmp(a_freq).then_with(|| a_name.cmp(b_name))
+    });
+    ranked.into_iter().map(|(rendered, _)| rendered).collect()
+}
+
+/// Walk every `Closure` in the file (other than the one at `skip`, the
+/// closure currently being written) and tally how often each rendered
+/// parameter appears.
+fn collect_fn_params(
+    node: &typst::syntax::LinkedNode,
+    skip: Range<usize>,
+    freq: &mut BTreeMap<EcoString, usize>,
+) {
+    if let Some(closure) = node.cast::<ast::Closure>() {
+        if closure.params().to_untyped().range() != skip {
+            for param in closure.params().children() {
+                if let Some(rendered) = render_param(param) {
+                    *freq.entry(rendered).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    for child in node.children() {
+        collect_fn_params(&child, skip.clone(), freq);
+    }
+}
+
+/// Render a parameter the way it would be typed, e.g. `body` or
+/// `loc: none`, so repeated ones can be tallied and reinserted verbatim.
+fn render_param(param: ast::Param) -> Option<EcoString> {
+    match param {
+        ast::Param::Pos(ast::Pattern::Normal(ast::Expr::Ident(ident))) => {
+            Some(ident.get().clone())
+        }
+        ast::Param::Pos(_) => None,
+        ast::Param::Named(named) => Some(eco_format!(
+            "{}: {}",
+            named.name().get(),
+            named.expr().to_untyped().clone().into_text()
+        )),
+        ast::Param::Spread(_) => None,
+    }
+}
+
 /// Add completions for the values of a named function parameter.
 pub fn named_param_value_completions<'a>(
     ctx: &mut CompletionContext<'a, '_>,
@@ -267,6 +645,7 @@ pub fn named_param_value_completions<'a>(
             apply: None,
             detail: Some(plain_docs_sentence(&param.docs)),
             command: None,
+            additional_text_edit: None,
         });
     }
 
@@ -278,4 +657,202 @@ pub fn named_param_value_completions<'a>(
     if ctx.before.ends_with(':') {
         ctx.enrich(" ", "");
     }
-}
\ No newline at end of file
+}
+
+/// A fixed postfix template: given the receiver's source text, produce the
+/// snippet that replaces `receiver.field`.
+type PostfixTemplate = fn(&str) -> EcoString;
+
+const POSTFIX_TEMPLATES: &[(&str, PostfixTemplate)] = &[
+    ("for", |r| eco_format!("for it in {r} {{\n\t$0\n}}")),
+    ("if", |r| eco_format!("if {r} {{\n\t$0\n}}")),
+    ("let", |r| eco_format!("let ${{1:name}} = {r}")),
+    ("repr", |r| eco_format!("repr({r})")),
+    ("str", |r| eco_format!("str({r})")),
+];
+
+/// Add postfix completions that rewrite the whole receiver expression rather
+/// than doing field access, e.g. `arr.for` -> `for it in arr { $0 }`.
+///
+/// Analogous to rust-analyzer's `postfix`/`format_like` assists: the
+/// completion's `additional_text_edit` deletes `receiver.` (the receiver and
+/// the dot), and `apply` carries the rewritten snippet that takes its place
+/// once the field name (the part still being typed) is replaced by the
+/// normal completion mechanism.
+///
+/// Suppressed when a method in the receiver's value type's scope already
+/// matches what's been typed, so postfix items don't clutter every single
+/// `.` press.
+pub fn postfix_completions<'a>(
+    ctx: &mut CompletionContext<'a, '_>,
+    field_access: ast::FieldAccess<'a>,
+) {
+    let in_math = matches!(
+        ctx.leaf.parent_kind(),
+        Some(SyntaxKind::Equation)
+            | Some(SyntaxKind::Math)
+            | Some(SyntaxKind::MathFrac)
+            | Some(SyntaxKind::MathAttach)
+    );
+    if in_math {
+        return;
+    }
+
+    let target = field_access.target();
+    let Some(target_node) = ctx.root.find(target.span()) else {
+        return;
+    };
+    let Some(field_node) = ctx.root.find(field_access.field().span()) else {
+        return;
+    };
+
+    let field_text = field_node.get().clone().into_text();
+    if has_matching_member(ctx, target, &field_text) {
+        return;
+    }
+
+    let receiver_text = target_node.get().clone().into_text();
+    let delete_range = target_node.range().start..field_node.range().start;
+
+    for (name, template) in POSTFIX_TEMPLATES {
+        ctx.completions.push(Completion {
+            kind: CompletionKind::Syntax,
+            label: (*name).into(),
+            apply: Some(template(&receiver_text)),
+            detail: None,
+            command: None,
+            additional_text_edit: Some((delete_range.clone(), EcoString::new())),
+        });
+    }
+}
+
+/// Whether `receiver`'s value type has a name in its scope (e.g. a method)
+/// that starts with `field_text`, i.e. whether normal method completion
+/// already applies here and postfix templates would just be noise. Fields of
+/// the value itself, such as dictionary keys, are not checked.
+///
+/// An empty `field_text` (the cursor right after the dot) never suppresses
+/// postfix completions, since nothing has been typed to narrow it down yet.
+fn has_matching_member(ctx: &CompletionContext, receiver: ast::Expr, field_text: &str) -> bool {
+    if field_text.is_empty() {
+        return false;
+    }
+
+    let Some(receiver_node) = ctx.root.find(receiver.span()) else {
+        return false;
+    };
+    let Some(value) = analyze_expr(ctx.ctx, &receiver_node) else {
+        return false;
+    };
+
+    value
+        .ty()
+        .scope()
+        .iter()
+        .any(|(name, _)| name.as_str().starts_with(field_text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typst::syntax::Source;
+
+    #[test]
+    fn import_insertion_point_is_start_of_file_without_existing_imports() {
+        let source = Source::detached("#let x = 1\n");
+        assert_eq!(import_insertion_point(&typst::syntax::LinkedNode::new(source.root())), 0);
+    }
+
+    #[test]
+    fn import_insertion_point_is_after_last_top_level_import() {
+        let text = "#import \"a.typ\": *\n#import \"b.typ\": *\n\n#let x = 1\n";
+        let source = Source::detached(text);
+        let root = typst::syntax::LinkedNode::new(source.root());
+        let insert_at = import_insertion_point(&root);
+        assert_eq!(insert_at, "#import \"a.typ\": *\n#import \"b.typ\": *".len());
+    }
+
+    #[test]
+    fn import_insertion_text_starts_on_its_own_line_after_an_existing_import() {
+        assert_eq!(import_insertion_text("c.typ", 0), "#import \"c.typ\": *\n");
+        assert_eq!(
+            import_insertion_text("c.typ", 42),
+            "\n#import \"c.typ\": *\n"
+        );
+    }
+
+    #[test]
+    fn rank_params_orders_by_frequency_then_name() {
+        let mut freq = BTreeMap::new();
+        freq.insert(EcoString::from("loc"), 1);
+        freq.insert(EcoString::from("body"), 3);
+        freq.insert(EcoString::from("it"), 3);
+
+        let ranked = rank_params(freq, &BTreeSet::new());
+        assert_eq!(ranked, vec!["body", "it", "loc"]);
+    }
+
+    #[test]
+    fn rank_params_excludes_names_already_in_the_current_param_list() {
+        let mut freq = BTreeMap::new();
+        freq.insert(EcoString::from("body"), 2);
+        freq.insert(EcoString::from("loc: none"), 1);
+
+        let mut current = BTreeSet::new();
+        current.insert(EcoString::from("loc"));
+
+        let ranked = rank_params(freq, &current);
+        assert_eq!(ranked, vec!["body"]);
+    }
+
+    /// Find the leaf just before `needle` in `text`, the way the cursor would
+    /// sit right after typing up to that point.
+    fn leaf_before<'a>(
+        root: &'a typst::syntax::LinkedNode<'a>,
+        text: &str,
+        needle: &str,
+    ) -> typst::syntax::LinkedNode<'a> {
+        let cursor = text.find(needle).expect("needle not found in text");
+        root.leaf_at(cursor, typst::syntax::Side::Before)
+            .expect("no leaf at cursor")
+    }
+
+    #[test]
+    fn param_list_ancestor_finds_the_params_being_written() {
+        let text = "#let f(nam) = nam\n";
+        let source = Source::detached(text);
+        let root = typst::syntax::LinkedNode::new(source.root());
+        let leaf = leaf_before(&root, text, ") = nam");
+        assert!(param_list_ancestor(&leaf).is_some());
+    }
+
+    #[test]
+    fn param_list_ancestor_does_not_cross_into_a_nested_default_value_call() {
+        // Cursor is inside `foo(1, <here>)`, itself inside `loc`'s default
+        // value, which is inside the outer `Params`. The nested `Args`
+        // boundary must stop the walk before it reaches that outer `Params`.
+        let text = "#let f(loc: foo(1, 2)) = loc\n";
+        let source = Source::detached(text);
+        let root = typst::syntax::LinkedNode::new(source.root());
+        let leaf = leaf_before(&root, text, "2)) = loc");
+        assert!(param_list_ancestor(&leaf).is_none());
+    }
+
+    #[test]
+    fn is_invalid_keyword_position_true_inside_nested_call_args() {
+        let text = "#f(x + 1)\n";
+        let source = Source::detached(text);
+        let root = typst::syntax::LinkedNode::new(source.root());
+        let leaf = leaf_before(&root, text, "1)");
+        assert!(is_invalid_keyword_position(&leaf));
+    }
+
+    #[test]
+    fn is_invalid_keyword_position_false_at_top_level_markup() {
+        let text = "Hello world\n";
+        let source = Source::detached(text);
+        let root = typst::syntax::LinkedNode::new(source.root());
+        let leaf = leaf_before(&root, text, "world");
+        assert!(!is_invalid_keyword_position(&leaf));
+    }
+}
@@ -0,0 +1,83 @@
+mod ext;
+
+pub use ext::*;
+
+use std::ops::Range;
+
+use ecow::EcoString;
+
+/// A single completion candidate collected by a [`CompletionContext`] pass,
+/// translated into an LSP `CompletionItem` once collection is done.
+#[derive(Debug, Clone)]
+pub struct Completion {
+    /// The kind of item this completes to.
+    pub kind: CompletionKind,
+    /// The label the completion is shown with.
+    pub label: EcoString,
+    /// The snippet to insert, if different from the label.
+    pub apply: Option<EcoString>,
+    /// A human-readable description shown alongside the label.
+    pub detail: Option<EcoString>,
+    /// A command to trigger once the completion has been applied.
+    pub command: Option<&'static str>,
+    /// An edit to apply alongside the completion itself, e.g. to insert the
+    /// `#import` a flyimport completion depends on, or to delete the
+    /// receiver text a postfix completion rewrites.
+    pub additional_text_edit: Option<(Range<usize>, EcoString)>,
+}
+
+/// The kind of a [`Completion`], used to pick an LSP `CompletionItemKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Func,
+    Variable,
+    Module,
+    Type,
+    Constant,
+    Param,
+    /// A syntax/snippet completion, e.g. a magic keyword or postfix template.
+    Syntax,
+}
+
+impl Completion {
+    /// Convert to the LSP representation, threading `additional_text_edit`
+    /// through to `additionalTextEdits` so editors apply both edits together.
+    pub fn to_lsp_item(&self, source: &typst::syntax::Source) -> lsp_types::CompletionItem {
+        let additional_text_edits = self.additional_text_edit.as_ref().map(|(range, text)| {
+            vec![lsp_types::TextEdit {
+                range: crate::lsp_typst_boundary::to_lsp_range(range.clone(), source),
+                new_text: text.to_string(),
+            }]
+        });
+
+        lsp_types::CompletionItem {
+            label: self.label.to_string(),
+            kind: Some(lsp_completion_kind(self.kind)),
+            detail: self.detail.as_ref().map(ToString::to_string),
+            insert_text: self.apply.as_ref().map(ToString::to_string),
+            insert_text_format: self
+                .apply
+                .is_some()
+                .then_some(lsp_types::InsertTextFormat::SNIPPET),
+            command: self.command.map(|command| lsp_types::Command {
+                title: String::new(),
+                command: command.to_string(),
+                arguments: None,
+            }),
+            additional_text_edits,
+            ..Default::default()
+        }
+    }
+}
+
+fn lsp_completion_kind(kind: CompletionKind) -> lsp_types::CompletionItemKind {
+    match kind {
+        CompletionKind::Func => lsp_types::CompletionItemKind::FUNCTION,
+        CompletionKind::Variable => lsp_types::CompletionItemKind::VARIABLE,
+        CompletionKind::Module => lsp_types::CompletionItemKind::MODULE,
+        CompletionKind::Type => lsp_types::CompletionItemKind::CLASS,
+        CompletionKind::Constant => lsp_types::CompletionItemKind::CONSTANT,
+        CompletionKind::Param => lsp_types::CompletionItemKind::VARIABLE,
+        CompletionKind::Syntax => lsp_types::CompletionItemKind::SNIPPET,
+    }
+}